@@ -46,6 +46,80 @@ pub struct Cli {
     /// エンコーダーの種類（mozjpeg=高品質・高圧縮率, image=imageクレートのJpegEncoder）
     #[arg(short = 'e', long, value_enum, default_value_t = EncoderType::Mozjpeg, help = "エンコーダーの種類を指定します。Mozjpeg（デフォルト）は高品質・高圧縮率、ImageはimageクレートのJpegEncoderを使用します。")]
     pub encoder: EncoderType,
+
+    /// リサイズ後の長辺の最大ピクセル数（指定しない場合はリサイズしない）
+    #[arg(
+        long,
+        help = "長辺がこの値を超える画像のみ、アスペクト比を維持したままリサイズしてから圧縮します。指定しない場合はリサイズを行いません。"
+    )]
+    pub max_dimension: Option<u32>,
+
+    /// リサイズ時に使用するフィルタ
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ResizeFilter::Lanczos3,
+        help = "--max-dimension 指定時にリサイズへ使用するフィルタを指定します（デフォルトはLanczos3）。"
+    )]
+    pub resize_filter: ResizeFilter,
+
+    /// 目標ファイルサイズ（KB単位、指定時は品質を二分探索で自動決定）
+    #[arg(
+        long,
+        help = "各ファイルを指定KB以下に収めるよう、品質を二分探索で自動決定します。指定しない場合は --quality を固定値として使用します。"
+    )]
+    pub target_size: Option<u64>,
+
+    /// PNGファイルに対するoxipngの最適化レベル（0〜6、高いほど圧縮率が高いが低速）
+    #[arg(long, default_value = "3", value_parser = png_level_validator, help = "入力に含まれるPNGファイルをロスレス再圧縮する際のoxipng最適化レベルを指定します。0から6の範囲で、デフォルトは3です。")]
+    pub png_level: u8,
+
+    /// 出力先をディレクトリツリーではなく単一のZIPアーカイブにする
+    #[arg(
+        long,
+        help = "指定した場合、出力ディレクトリへのファイル書き込みの代わりに、相対パスとmtimeを保持したまま単一のZIPアーカイブへ書き込みます。"
+    )]
+    pub output_zip: Option<String>,
+}
+
+/// リサイズフィルタの種類の列挙型
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ResizeFilter {
+    /// 最近傍補間（最速・低品質）
+    Nearest,
+    /// 線形補間
+    Triangle,
+    /// 3次補間
+    CatmullRom,
+    /// ガウシアンフィルタ
+    Gaussian,
+    /// Lanczos3（デフォルト、高品質）
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    /// `image` クレートの `FilterType` に変換する
+    pub fn to_image_filter(self) -> image::imageops::FilterType {
+        match self {
+            ResizeFilter::Nearest => image::imageops::FilterType::Nearest,
+            ResizeFilter::Triangle => image::imageops::FilterType::Triangle,
+            ResizeFilter::CatmullRom => image::imageops::FilterType::CatmullRom,
+            ResizeFilter::Gaussian => image::imageops::FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+        }
+    }
+}
+
+impl fmt::Display for ResizeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResizeFilter::Nearest => write!(f, "nearest"),
+            ResizeFilter::Triangle => write!(f, "triangle"),
+            ResizeFilter::CatmullRom => write!(f, "catmull-rom"),
+            ResizeFilter::Gaussian => write!(f, "gaussian"),
+            ResizeFilter::Lanczos3 => write!(f, "lanczos3"),
+        }
+    }
 }
 
 /// ログレベルの列挙型
@@ -65,6 +139,10 @@ pub enum EncoderType {
     Mozjpeg,
     /// imageクレートのJpegEncoder
     Image,
+    /// WebPエンコーダー（要 webp-encoder フィーチャー）
+    Webp,
+    /// AVIFエンコーダー（要 avif-encoder フィーチャー）
+    Avif,
 }
 
 impl fmt::Display for EncoderType {
@@ -72,6 +150,8 @@ impl fmt::Display for EncoderType {
         match self {
             EncoderType::Mozjpeg => write!(f, "mozjpeg"),
             EncoderType::Image => write!(f, "image"),
+            EncoderType::Webp => write!(f, "webp"),
+            EncoderType::Avif => write!(f, "avif"),
         }
     }
 }
@@ -101,6 +181,19 @@ fn quality_validator(s: &str) -> Result<u8, String> {
         })
 }
 
+/// PNG最適化レベルパラメータのバリデーション（0-6の範囲内であることを確認）
+fn png_level_validator(s: &str) -> Result<u8, String> {
+    s.parse::<u8>()
+        .map_err(|_| format!("`{}` は有効な数値ではありません", s))
+        .and_then(|level| {
+            if (0..=6).contains(&level) {
+                Ok(level)
+            } else {
+                Err("PNG最適化レベルは0から6の間である必要があります".to_string())
+            }
+        })
+}
+
 /// スレッド数パラメータのバリデーション
 fn threads_validator(s: &str) -> Result<usize, String> {
     s.parse::<usize>()
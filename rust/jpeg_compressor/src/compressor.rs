@@ -1,15 +1,19 @@
 use std::fs;
-use std::io::{BufWriter, Read, Write};
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Local, Timelike};
 use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GenericImageView, imageops::FilterType};
 use indicatif::{ProgressBar, ProgressStyle};
 use log::{error, info, warn};
 use rayon::prelude::*;
 use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
 
 use crate::cli::EncoderType;
 use mozjpeg::{ColorSpace, Compress};
@@ -23,8 +27,11 @@ pub struct CompressionStats {
     pub processed_files: usize,
     pub skipped_files: usize,
     pub error_files: usize,
+    pub resized_files: usize,
     pub original_size: u64,
     pub compressed_size: u64,
+    /// 採用された品質値の合計（`processed_files` で割ると平均品質になる）
+    pub selected_quality_total: u64,
     pub start_time: Instant,
 }
 
@@ -34,8 +41,10 @@ impl Default for CompressionStats {
             processed_files: 0,
             skipped_files: 0,
             error_files: 0,
+            resized_files: 0,
             original_size: 0,
             compressed_size: 0,
+            selected_quality_total: 0,
             start_time: Instant::now(),
         }
     }
@@ -60,6 +69,14 @@ impl CompressionStats {
         1.0 - self.get_size_ratio()
     }
 
+    /// 採用された品質の平均値を計算
+    pub fn get_average_quality(&self) -> f64 {
+        if self.processed_files == 0 {
+            return 0.0;
+        }
+        self.selected_quality_total as f64 / self.processed_files as f64
+    }
+
     /// 処理速度（ファイル/秒）を計算
     pub fn get_processing_speed(&self) -> f64 {
         let elapsed = self.start_time.elapsed().as_secs_f64();
@@ -109,16 +126,32 @@ pub fn compress_jpeg_directory(
     quality: u8,
     thread_count: usize,
     encoder_type: EncoderType,
+    max_dimension: Option<u32>,
+    resize_filter: FilterType,
+    target_size_bytes: Option<u64>,
+    png_level: u8,
+    output_zip: Option<&Path>,
 ) -> Result<CompressionStats> {
     info!("JPEGファイルをスキャンしています...");
 
-    // 出力ディレクトリの作成
-    fs::create_dir_all(output_dir).with_context(|| {
-        format!(
-            "出力ディレクトリの作成に失敗しました: {}",
-            output_dir.display()
-        )
-    })?;
+    // 出力先の準備 - ZIP指定時は単一アーカイブへ、それ以外はディレクトリツリーへ出力する
+    let zip_writer: Option<Mutex<ZipWriter<fs::File>>> = match output_zip {
+        Some(zip_path) => {
+            let zip_file = fs::File::create(zip_path).with_context(|| {
+                format!("ZIPファイルを作成できませんでした: {}", zip_path.display())
+            })?;
+            Some(Mutex::new(ZipWriter::new(zip_file)))
+        }
+        None => {
+            fs::create_dir_all(output_dir).with_context(|| {
+                format!(
+                    "出力ディレクトリの作成に失敗しました: {}",
+                    output_dir.display()
+                )
+            })?;
+            None
+        }
+    };
 
     // ファイルのパスを収集
     let mut files = Vec::new();
@@ -130,10 +163,13 @@ pub fn compress_jpeg_directory(
             continue;
         }
 
-        // JPEGファイルのみ処理対象にする
+        // 入力として許容する拡張子（WebP/AVIF出力先のため、JPEG以外のデコード可能形式も許容する）
         if let Some(ext) = path.extension() {
             let ext_lower = ext.to_string_lossy().to_lowercase();
-            if ext_lower == "jpg" || ext_lower == "jpeg" {
+            if matches!(
+                ext_lower.as_str(),
+                "jpg" | "jpeg" | "png" | "webp" | "bmp" | "tiff" | "gif"
+            ) {
                 files.push(path.to_path_buf());
             }
         }
@@ -241,80 +277,151 @@ pub fn compress_jpeg_directory(
         .num_threads(thread_count)
         .build_global()?;
 
+    // 品質ベースのエンコーダー（PNG以外）のコーデックは全ファイルで共通のため一度だけ生成する
+    let codec: Box<dyn ImageCodec> = codec_for(encoder_type);
+
     // 並列処理で圧縮を実行
     files.par_iter().for_each(|file_path| {
         let relative_path = file_path.strip_prefix(input_dir).unwrap_or(file_path);
-        let output_file = output_dir.join(relative_path);
-
-        // 出力ディレクトリが存在しない場合は作成
-        if let Some(parent) = output_file.parent() {
-            if !parent.exists() {
-                if let Err(e) = fs::create_dir_all(parent) {
-                    error!(
-                        "ディレクトリの作成に失敗しました {}: {}",
-                        parent.display(),
-                        e
-                    );
-                    let mut stats = stats.lock().unwrap();
-                    stats.error_files += 1;
-                    return;
+        let is_png = file_path
+            .extension()
+            .is_some_and(|ext| ext.to_string_lossy().to_lowercase() == "png");
+        // PNGは入力拡張子からロスレス用の専用コーデックを選ぶ（`EncoderType` にPNGは無いため）
+        let png_codec;
+        let file_codec: &dyn ImageCodec = if is_png {
+            png_codec = PngCodec { level: png_level };
+            &png_codec
+        } else {
+            codec.as_ref()
+        };
+        let relative_output_path = relative_path.with_extension(file_codec.output_extension());
+        let output_file = output_dir.join(&relative_output_path);
+
+        // ディスク出力時のみ、出力ディレクトリが存在しない場合は作成する（ZIP出力時は不要）
+        if zip_writer.is_none() {
+            if let Some(parent) = output_file.parent() {
+                if !parent.exists() {
+                    if let Err(e) = fs::create_dir_all(parent) {
+                        error!(
+                            "ディレクトリの作成に失敗しました {}: {}",
+                            parent.display(),
+                            e
+                        );
+                        let mut stats = stats.lock().unwrap();
+                        stats.error_files += 1;
+                        return;
+                    }
                 }
             }
         }
 
-        // 圧縮を実行 - エンコーダータイプに基づいて関数を選択
+        // 元のファイルのメタデータを取得
         let start = Instant::now();
-        let compression_result = match encoder_type {
-            EncoderType::Mozjpeg => compress_jpeg_mozjpeg(file_path, &output_file, quality),
-            EncoderType::Image => compress_jpeg_image(file_path, &output_file, quality),
+        let metadata = match fs::metadata(file_path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                error!(
+                    "ファイルのメタデータを取得できません {}: {}",
+                    file_path.display(),
+                    e
+                );
+                let mut stats = stats.lock().unwrap();
+                stats.error_files += 1;
+                progress_bar.inc(1);
+                return;
+            }
         };
+        let original_size = metadata.len();
+        let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
 
-        // 圧縮結果の処理
-        match compression_result {
-            Ok((original_size, compressed_size)) => {
-                let ratio = if original_size > 0 {
-                    compressed_size as f64 / original_size as f64 * 100.0
-                } else {
-                    0.0
-                };
-
-                // サイズの大きな変化があった場合のみログを出力
-                let size_change_pct = if original_size > 0 {
-                    (1.0 - (compressed_size as f64 / original_size as f64)) * 100.0
-                } else {
-                    0.0
-                };
-
-                if size_change_pct > 70.0
-                    || original_size > 10 * 1024 * 1024
-                    || start.elapsed().as_secs() > 5
-                {
-                    info!(
-                        "注目ファイル: {} ({} → {}, {:.1}%, {:.1}秒)",
-                        relative_path.display(),
-                        format_bytes(original_size),
-                        format_bytes(compressed_size),
-                        ratio,
-                        start.elapsed().as_secs_f64()
-                    );
-                }
-
-                // 統計情報を更新
+        // デコード → （任意のリサイズ） → エンコードの共通パス（PNGも含む全フォーマット共通）
+        let decode_result = decode_and_resize(file_path, max_dimension, resize_filter);
+        let (img, resized) = match decode_result {
+            Ok(result) => result,
+            Err(e) => {
+                error!("画像のデコードに失敗しました {}: {}", file_path.display(), e);
                 let mut stats = stats.lock().unwrap();
-                stats.processed_files += 1;
-                stats.original_size += original_size;
-                stats.compressed_size += compressed_size;
-
-                // 処理後にプログレスバーを更新
+                stats.error_files += 1;
                 progress_bar.inc(1);
+                return;
             }
+        };
+
+        // エンコードを実行 - 目標サイズ指定時は品質を二分探索、それ以外は固定品質
+        // （品質で出力が変化しないコーデック＝PNGは、二分探索しても結果が変わらず
+        // 毎回oxipngを再実行するだけなので、常に1回のエンコードで済ませる）
+        let encode_result = match target_size_bytes {
+            Some(target_bytes) if file_codec.supports_quality() => {
+                find_target_quality(file_codec, &img, target_bytes)
+            }
+            _ => encode_with(file_codec, &img, quality).map(|bytes| (quality, bytes)),
+        };
+
+        let (used_quality, encoded) = match encode_result {
+            Ok(result) => result,
             Err(e) => {
                 error!("圧縮エラー {}: {}", file_path.display(), e);
                 let mut stats = stats.lock().unwrap();
                 stats.error_files += 1;
                 progress_bar.inc(1);
+                return;
             }
+        };
+
+        // エンコード結果をまとめて書き込む
+        if let Err(e) = write_output(
+            zip_writer.as_ref(),
+            &output_file,
+            &relative_output_path,
+            &encoded,
+            modified,
+        ) {
+            error!("出力に失敗しました {}: {}", output_file.display(), e);
+            let mut stats = stats.lock().unwrap();
+            stats.error_files += 1;
+            progress_bar.inc(1);
+            return;
         }
+
+        let compressed_size = encoded.len() as u64;
+        let ratio = if original_size > 0 {
+            compressed_size as f64 / original_size as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        // サイズの大きな変化があった場合のみログを出力
+        let size_change_pct = if original_size > 0 {
+            (1.0 - (compressed_size as f64 / original_size as f64)) * 100.0
+        } else {
+            0.0
+        };
+
+        if size_change_pct > 70.0 || original_size > 10 * 1024 * 1024 || start.elapsed().as_secs() > 5
+        {
+            info!(
+                "注目ファイル: {} ({} → {}, {:.1}%, 品質{}, {:.1}秒)",
+                relative_path.display(),
+                format_bytes(original_size),
+                format_bytes(compressed_size),
+                ratio,
+                used_quality,
+                start.elapsed().as_secs_f64()
+            );
+        }
+
+        // 統計情報を更新
+        let mut stats = stats.lock().unwrap();
+        stats.processed_files += 1;
+        if resized {
+            stats.resized_files += 1;
+        }
+        stats.original_size += original_size;
+        stats.compressed_size += compressed_size;
+        stats.selected_quality_total += used_quality as u64;
+
+        // 処理後にプログレスバーを更新
+        progress_bar.inc(1);
     });
 
     // プログレスバー終了処理
@@ -327,6 +434,15 @@ pub fn compress_jpeg_directory(
         manager.disable();
     }
 
+    // ZIP出力時は中央ディレクトリを書き込んでアーカイブを完結させる
+    if let Some(writer) = zip_writer {
+        writer
+            .into_inner()
+            .unwrap()
+            .finish()
+            .with_context(|| "ZIPファイルの完了処理に失敗しました")?;
+    }
+
     let final_stats = Arc::try_unwrap(stats).unwrap().into_inner().unwrap();
 
     // トータル統計情報のみログに出力
@@ -343,91 +459,258 @@ pub fn compress_jpeg_directory(
     Ok(final_stats)
 }
 
-/// imageクレートのJpegEncoderを使用した圧縮実装（既存の実装）
-fn compress_jpeg_image(input_path: &Path, output_path: &Path, quality: u8) -> Result<(u64, u64)> {
-    // 元のファイルサイズを取得
-    let original_size = fs::metadata(input_path)
-        .with_context(|| {
-            format!(
-                "ファイルのメタデータを取得できません: {}",
-                input_path.display()
-            )
-        })?
-        .len();
-
-    // バイナリデータとして読み込む
-    let mut input_file = fs::File::open(input_path)
-        .with_context(|| format!("入力ファイルを開けませんでした: {}", input_path.display()))?;
-
-    // 画像全体をメモリに読み込む
-    let mut buffer = Vec::new();
-    input_file
-        .read_to_end(&mut buffer)
-        .with_context(|| format!("ファイルの読み込みに失敗しました: {}", input_path.display()))?;
+/// 画像をデコードし、必要な場合のみアスペクト比を維持してリサイズする
+///
+/// `max_dimension` が指定されていて、長辺がそれを超える場合のみリサイズを行う（no-op境界を含む）。
+/// 戻り値の `bool` はリサイズを実施したかどうかを表す。
+fn decode_and_resize(
+    input_path: &Path,
+    max_dimension: Option<u32>,
+    resize_filter: FilterType,
+) -> Result<(DynamicImage, bool)> {
+    let img = image::open(input_path)
+        .with_context(|| format!("画像ファイルを開けませんでした: {}", input_path.display()))?;
 
-    // 画像をデコード
-    let img = image::load_from_memory(&buffer)
-        .with_context(|| format!("画像データの解析に失敗しました: {}", input_path.display()))?;
+    let Some(max_dim) = max_dimension else {
+        return Ok((img, false));
+    };
 
-    // バッファ付きの書き込み
-    let output_file = fs::File::create(output_path).with_context(|| {
-        format!(
-            "出力ファイルを作成できませんでした: {}",
-            output_path.display()
-        )
-    })?;
-    let buffered_output = BufWriter::new(output_file);
+    let (width, height) = img.dimensions();
+    if width <= max_dim && height <= max_dim {
+        return Ok((img, false));
+    }
+
+    Ok((img.resize(max_dim, max_dim, resize_filter), true))
+}
+
+/// エンコード済みのバイト列を出力先（ディレクトリツリー or ZIPアーカイブ）に書き込む
+///
+/// ZIP出力時は `zip_writer`（単一の `ZipWriter` を `Mutex` で保護したもの）へ
+/// 集約することで、並列ワーカーからの書き込みを単一箇所に直列化する。
+fn write_output(
+    zip_writer: Option<&Mutex<ZipWriter<fs::File>>>,
+    output_file: &Path,
+    relative_path: &Path,
+    data: &[u8],
+    modified: SystemTime,
+) -> Result<()> {
+    match zip_writer {
+        Some(writer) => write_zip_entry(writer, relative_path, data, modified),
+        None => fs::write(output_file, data)
+            .with_context(|| format!("出力ファイルを作成できませんでした: {}", output_file.display())),
+    }
+}
+
+/// ZIPアーカイブへ1エントリを書き込む
+///
+/// 画像データは既に圧縮済みのため、ZIP側はSTORED（無圧縮）にして二重圧縮を避ける。
+fn write_zip_entry(
+    zip_writer: &Mutex<ZipWriter<fs::File>>,
+    relative_path: &Path,
+    data: &[u8],
+    modified: SystemTime,
+) -> Result<()> {
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .last_modified_time(system_time_to_zip_datetime(modified));
+
+    // ZIPの中央ディレクトリ整合性のため、書き込みはこのMutexで単一スレッドに集約する
+    let mut zip = zip_writer.lock().unwrap();
+    zip.start_file(relative_path.to_string_lossy(), options)
+        .with_context(|| format!("ZIPエントリの作成に失敗しました: {}", relative_path.display()))?;
+    zip.write_all(data)
+        .with_context(|| format!("ZIPへの書き込みに失敗しました: {}", relative_path.display()))?;
+
+    Ok(())
+}
+
+/// `SystemTime` を `zip` クレートのDOS日時形式に変換する
+fn system_time_to_zip_datetime(time: SystemTime) -> zip::DateTime {
+    let datetime: DateTime<Local> = time.into();
+    zip::DateTime::from_date_and_time(
+        datetime.year() as u16,
+        datetime.month() as u8,
+        datetime.day() as u8,
+        datetime.hour() as u8,
+        datetime.minute() as u8,
+        datetime.second() as u8,
+    )
+    .unwrap_or_default()
+}
+
+/// 画像コーデックの共通インターフェース
+///
+/// `compress_jpeg_directory` はこのトレイトを介して、mozjpeg/image(JPEG)/WebP/AVIF/PNGの
+/// 全フォーマットを「デコード→`encode`→サイズ計測」という単一パスで扱う。
+pub trait ImageCodec: Send + Sync {
+    /// このコーデックが出力するファイルの拡張子（ドットなし）
+    fn output_extension(&self) -> &str;
+
+    /// 画像を指定品質でエンコードし `out` へ書き込む
+    fn encode(&self, img: &DynamicImage, out: &mut dyn Write, quality: u8) -> Result<()>;
+
+    /// `quality` によって出力サイズが変化するかどうか
+    ///
+    /// `false` を返すコーデック（PNGなどロスレス形式）は、`--target-size` 指定時でも
+    /// 品質の二分探索を行わず1回のエンコードで済ませる（`find_target_quality` 参照）。
+    fn supports_quality(&self) -> bool {
+        true
+    }
+}
+
+/// mozjpegコーデック
+struct MozjpegCodec;
+
+impl ImageCodec for MozjpegCodec {
+    fn output_extension(&self) -> &str {
+        "jpg"
+    }
+
+    fn encode(&self, img: &DynamicImage, out: &mut dyn Write, quality: u8) -> Result<()> {
+        out.write_all(&encode_jpeg_mozjpeg(img, quality)?)
+            .with_context(|| "エンコード結果の書き込みに失敗しました")
+    }
+}
+
+/// imageクレートのJpegEncoderを使用したコーデック
+struct ImageJpegCodec;
+
+impl ImageCodec for ImageJpegCodec {
+    fn output_extension(&self) -> &str {
+        "jpg"
+    }
+
+    fn encode(&self, img: &DynamicImage, out: &mut dyn Write, quality: u8) -> Result<()> {
+        out.write_all(&encode_jpeg_image(img, quality)?)
+            .with_context(|| "エンコード結果の書き込みに失敗しました")
+    }
+}
+
+/// WebPコーデック
+struct WebpCodec;
+
+impl ImageCodec for WebpCodec {
+    // `webp-encoder` フィーチャーが無効な場合、`encode` はJPEGへフォールバックするため、
+    // 拡張子もそれに合わせてJPEGを返す（さもないと `.webp` を名乗るJPEGファイルができる）
+    #[cfg(feature = "webp-encoder")]
+    fn output_extension(&self) -> &str {
+        "webp"
+    }
+
+    #[cfg(not(feature = "webp-encoder"))]
+    fn output_extension(&self) -> &str {
+        "jpg"
+    }
+
+    fn encode(&self, img: &DynamicImage, out: &mut dyn Write, quality: u8) -> Result<()> {
+        out.write_all(&encode_webp(img, quality)?)
+            .with_context(|| "エンコード結果の書き込みに失敗しました")
+    }
+}
+
+/// AVIFコーデック
+struct AvifCodec;
+
+impl ImageCodec for AvifCodec {
+    // `avif-encoder` フィーチャーが無効な場合、`encode` はJPEGへフォールバックするため、
+    // 拡張子もそれに合わせてJPEGを返す（さもないと `.avif` を名乗るJPEGファイルができる）
+    #[cfg(feature = "avif-encoder")]
+    fn output_extension(&self) -> &str {
+        "avif"
+    }
+
+    #[cfg(not(feature = "avif-encoder"))]
+    fn output_extension(&self) -> &str {
+        "jpg"
+    }
+
+    fn encode(&self, img: &DynamicImage, out: &mut dyn Write, quality: u8) -> Result<()> {
+        out.write_all(&encode_avif(img, quality)?)
+            .with_context(|| "エンコード結果の書き込みに失敗しました")
+    }
+}
 
-    // JpegEncoderを直接使用して品質パラメータを適用
-    let mut encoder = JpegEncoder::new_with_quality(buffered_output, quality);
+/// PNGコーデック
+///
+/// `quality` は無視する（PNGはロスレス形式のため）。デコード済みの画像をPNGとして
+/// 再エンコードした上でoxipngにより最適化するため、ピクセルデータが変化しない限り
+/// （＝`--max-dimension` でリサイズされない限り）画質劣化は発生しない。
+struct PngCodec {
+    level: u8,
+}
+
+impl ImageCodec for PngCodec {
+    fn output_extension(&self) -> &str {
+        "png"
+    }
+
+    fn supports_quality(&self) -> bool {
+        false
+    }
+
+    fn encode(&self, img: &DynamicImage, out: &mut dyn Write, _quality: u8) -> Result<()> {
+        let mut png_bytes = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+            .with_context(|| "PNGへの再エンコードに失敗しました")?;
+
+        let options = oxipng::Options::from_preset(self.level);
+        let optimized = oxipng::optimize_from_memory(&png_bytes, &options)
+            .with_context(|| "PNGの最適化に失敗しました")?;
+
+        let best = if optimized.len() < png_bytes.len() {
+            optimized
+        } else {
+            png_bytes
+        };
+
+        out.write_all(&best)
+            .with_context(|| "エンコード結果の書き込みに失敗しました")
+    }
+}
+
+/// `EncoderType` に対応する `ImageCodec` を生成するファクトリ
+///
+/// PNGは入力拡張子に応じて個別に選択されるため（`EncoderType` にPNGは存在しない）、
+/// ここでは品質ベースの4フォーマットのみを扱う。
+fn codec_for(encoder_type: EncoderType) -> Box<dyn ImageCodec> {
+    match encoder_type {
+        EncoderType::Mozjpeg => Box::new(MozjpegCodec),
+        EncoderType::Image => Box::new(ImageJpegCodec),
+        EncoderType::Webp => Box::new(WebpCodec),
+        EncoderType::Avif => Box::new(AvifCodec),
+    }
+}
+
+/// 指定したコーデック・品質で画像をメモリ上にエンコードする
+///
+/// すべてのバックエンドをメモリバッファ経由に揃えることで、`--target-size`
+/// の二分探索のようにファイルへ書き出す前に複数回エンコードを試せるようにする。
+fn encode_with(codec: &dyn ImageCodec, img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    codec.encode(img, &mut buffer, quality)?;
+    Ok(buffer)
+}
+
+/// imageクレートのJpegEncoderを使用したエンコード実装（既存の実装）
+fn encode_jpeg_image(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut buffer, quality);
     encoder
-        .encode_image(&img)
-        .with_context(|| format!("画像のエンコードに失敗しました: {}", input_path.display()))?;
-
-    // 圧縮後のファイルサイズを取得
-    let compressed_size = fs::metadata(output_path)
-        .with_context(|| {
-            format!(
-                "圧縮ファイルのメタデータを取得できません: {}",
-                output_path.display()
-            )
-        })?
-        .len();
-
-    Ok((original_size, compressed_size))
+        .encode_image(img)
+        .with_context(|| "画像のエンコードに失敗しました")?;
+    Ok(buffer)
 }
 
-/// mozjpegを使用した高品質圧縮実装
+/// mozjpegを使用した高品質エンコード実装
 #[cfg(feature = "mozjpeg-encoder")]
-fn compress_jpeg_mozjpeg(input_path: &Path, output_path: &Path, quality: u8) -> Result<(u64, u64)> {
-    // 元のファイルサイズを取得
-    let original_size = fs::metadata(input_path)
-        .with_context(|| {
-            format!(
-                "ファイルのメタデータを取得できません: {}",
-                input_path.display()
-            )
-        })?
-        .len();
-
-    // 画像を読み込む
-    let img = image::open(input_path)
-        .with_context(|| format!("画像ファイルを開けませんでした: {}", input_path.display()))?;
-
+fn encode_jpeg_mozjpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
     // RGBに変換
     let rgb_img = img.to_rgb8();
     let width = rgb_img.width() as usize;
     let height = rgb_img.height() as usize;
     let pixels = rgb_img.into_raw();
 
-    // 出力ファイルを準備
-    let output_file = fs::File::create(output_path).with_context(|| {
-        format!(
-            "出力ファイルを作成できませんでした: {}",
-            output_path.display()
-        )
-    })?;
-    let mut buffered_output = BufWriter::new(output_file);
+    let mut buffer = Vec::new();
 
     // mozjpeg圧縮設定 - 実際のAPI（0.10.13）に合わせた実装
     let mut comp = Compress::new(ColorSpace::JCS_RGB);
@@ -437,47 +720,105 @@ fn compress_jpeg_mozjpeg(input_path: &Path, output_path: &Path, quality: u8) ->
 
     // 正しくAPIを使用する
     let mut comp_started = comp
-        .start_compress(&mut buffered_output)
-        .with_context(|| format!("mozjpegの圧縮開始に失敗しました: {}", input_path.display()))?;
+        .start_compress(&mut buffer)
+        .with_context(|| "mozjpegの圧縮開始に失敗しました")?;
     // ピクセルデータを書き込む
-    comp_started.write_scanlines(&pixels).with_context(|| {
-        format!(
-            "画像データの書き込みに失敗しました: {}",
-            input_path.display()
-        )
-    })?;
+    comp_started
+        .write_scanlines(&pixels)
+        .with_context(|| "画像データの書き込みに失敗しました")?;
     // 終了処理（非推奨のfinish_compressではなくfinishを使用）
     comp_started
         .finish()
-        .with_context(|| format!("mozjpegの圧縮完了に失敗しました: {}", input_path.display()))?;
-
-    // バッファをフラッシュ
-    buffered_output
-        .flush()
-        .with_context(|| "出力バッファのフラッシュに失敗しました")?;
-
-    // 圧縮後のファイルサイズを取得
-    let compressed_size = fs::metadata(output_path)
-        .with_context(|| {
-            format!(
-                "圧縮ファイルのメタデータを取得できません: {}",
-                output_path.display()
-            )
-        })?
-        .len();
-
-    Ok((original_size, compressed_size))
+        .with_context(|| "mozjpegの圧縮完了に失敗しました")?;
+
+    Ok(buffer)
 }
 
 /// エンコーダーが利用できない場合の代替実装
 #[cfg(not(feature = "mozjpeg-encoder"))]
-fn compress_jpeg_mozjpeg(input_path: &Path, output_path: &Path, quality: u8) -> Result<(u64, u64)> {
+fn encode_jpeg_mozjpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
     // mozjpegが利用できない場合は標準のエンコーダーを使用
     warn!("mozjpegエンコーダーが利用できません。標準のimageエンコーダーを使用します。");
-    info!(
-        "fallback: {} を圧縮します (品質: {})",
-        input_path.display(),
-        quality
-    );
-    compress_jpeg_image(input_path, output_path, quality)
+    info!("fallback: 品質 {} でエンコードします", quality);
+    encode_jpeg_image(img, quality)
+}
+
+/// WebPエンコーダーを使用したエンコード実装
+#[cfg(feature = "webp-encoder")]
+fn encode_webp(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = (rgb_img.width(), rgb_img.height());
+
+    let encoded = webp::Encoder::from_rgb(&rgb_img, width, height).encode(quality as f32);
+
+    Ok(encoded.to_vec())
+}
+
+/// WebPエンコーダーが利用できない場合の代替実装
+#[cfg(not(feature = "webp-encoder"))]
+fn encode_webp(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    warn!("webp-encoderフィーチャーが有効になっていません。JPEGにフォールバックします。");
+    encode_jpeg_image(img, quality)
+}
+
+/// AVIFエンコーダーを使用したエンコード実装
+#[cfg(feature = "avif-encoder")]
+fn encode_avif(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = (rgb_img.width() as usize, rgb_img.height() as usize);
+
+    let encoded = ravif::Encoder::new()
+        .with_quality(quality as f32)
+        .encode_rgb(ravif::Img::new(
+            bytemuck::cast_slice(rgb_img.as_raw()),
+            width,
+            height,
+        ))
+        .with_context(|| "AVIFエンコードに失敗しました")?;
+
+    Ok(encoded.avif_file)
+}
+
+/// AVIFエンコーダーが利用できない場合の代替実装
+#[cfg(not(feature = "avif-encoder"))]
+fn encode_avif(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    warn!("avif-encoderフィーチャーが有効になっていません。JPEGにフォールバックします。");
+    encode_jpeg_image(img, quality)
+}
+
+/// 指定した目標サイズ（バイト）以下に収まる最高品質を二分探索で求める
+///
+/// 下限 `lo=1`・上限 `hi=100` で探索し、目標サイズ以下ならより高品質を狙って
+/// `lo=mid+1`、超過していれば `hi=mid-1` とする。探索過程で得た中で「目標を
+/// 満たした中で最良」の結果を `best` に保持し、`lo>hi` になった時点で打ち切る。
+/// 一つも目標を満たせなかった場合は、最小品質（`lo=1`）の結果を返す。
+fn find_target_quality(
+    codec: &dyn ImageCodec,
+    img: &DynamicImage,
+    target_bytes: u64,
+) -> Result<(u8, Vec<u8>)> {
+    let mut lo: i32 = 1;
+    let mut hi: i32 = 100;
+    let mut best: Option<(u8, Vec<u8>)> = None;
+
+    while lo <= hi {
+        let mid = ((lo + hi) / 2) as u8;
+        let encoded = encode_with(codec, img, mid)?;
+
+        if encoded.len() as u64 <= target_bytes {
+            best = Some((mid, encoded));
+            lo = mid as i32 + 1;
+        } else {
+            hi = mid as i32 - 1;
+        }
+    }
+
+    match best {
+        Some(result) => Ok(result),
+        None => {
+            // 目標を満たす品質が見つからなかった場合は最小品質（最小サイズ）の結果を返す
+            let encoded = encode_with(codec, img, 1)?;
+            Ok((1, encoded))
+        }
+    }
 }
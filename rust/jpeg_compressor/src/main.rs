@@ -39,8 +39,8 @@ fn main() -> Result<()> {
     // 圧縮設定の表示
     display_compression_config(&cli, &input_dir, &output_dir);
 
-    // 出力ディレクトリが存在する場合の確認
-    if output_dir.exists() && !cli.yes {
+    // 出力ディレクトリが存在する場合の確認（ZIP出力時はディレクトリツリーへ書き込まないため対象外）
+    if cli.output_zip.is_none() && output_dir.exists() && !cli.yes {
         println!("出力ディレクトリが既に存在します: {}", output_dir.display());
         print!("既存のファイルを上書きしますか？ (y/n): ");
         io::stdout().flush()?;
@@ -53,6 +53,22 @@ fn main() -> Result<()> {
         }
     }
 
+    // 出力ZIPが既に存在する場合の確認（`fs::File::create` は無言で上書き・切り詰めを行うため）
+    if let Some(zip_path) = &cli.output_zip {
+        if Path::new(zip_path).exists() && !cli.yes {
+            println!("出力ZIPファイルが既に存在します: {}", zip_path);
+            print!("上書きしますか？ (y/n): ");
+            io::stdout().flush()?;
+
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            if input.trim().to_lowercase() != "y" {
+                println!("処理を中止しました。");
+                return Ok(());
+            }
+        }
+    }
+
     // 自動実行でない場合はユーザー確認
     if !cli.yes {
         print!("圧縮処理を実行しますか？ (y/n): ");
@@ -84,8 +100,19 @@ fn main() -> Result<()> {
     );
 
     // 圧縮処理の実行
-    let stats = compress_jpeg_directory(&input_dir, &output_dir, cli.quality, threads, cli.encoder)
-        .with_context(|| "JPEG圧縮処理中にエラーが発生しました")?;
+    let stats = compress_jpeg_directory(
+        &input_dir,
+        &output_dir,
+        cli.quality,
+        threads,
+        cli.encoder,
+        cli.max_dimension,
+        cli.resize_filter.to_image_filter(),
+        cli.target_size.map(|kb| kb * 1024),
+        cli.png_level,
+        cli.output_zip.as_deref().map(Path::new),
+    )
+    .with_context(|| "JPEG圧縮処理中にエラーが発生しました")?;
 
     // 処理時間の計算
     let elapsed = start_time.elapsed();
@@ -123,6 +150,23 @@ fn display_compression_config(cli: &Cli, input_dir: &Path, output_dir: &Path) {
         }
     );
     info!(" - エンコーダー: {}", cli.encoder);
+    info!(
+        " - 最大辺長: {}",
+        cli.max_dimension
+            .map(|d| d.to_string())
+            .unwrap_or_else(|| "制限なし".to_string())
+    );
+    info!(" - リサイズフィルタ: {}", cli.resize_filter);
+    info!(
+        " - 目標サイズ: {}",
+        cli.target_size
+            .map(|kb| format!("{} KB", kb))
+            .unwrap_or_else(|| "指定なし（固定品質）".to_string())
+    );
+    info!(" - PNG最適化レベル: {}", cli.png_level);
+    if let Some(zip_path) = &cli.output_zip {
+        info!(" - 出力先: ZIPアーカイブ ({})", zip_path);
+    }
     info!(" - ログレベル: {}", cli.log_level);
     info!("--------------------------------------------------");
 }
@@ -160,11 +204,13 @@ fn display_compression_results(
     );
     info!("処理速度: {:.1}ファイル/秒", speed);
     info!("処理ファイル数: {}", stats.processed_files);
+    info!("リサイズ済み枚数: {}", stats.resized_files);
     info!("スキップファイル数: {}", stats.skipped_files);
     info!("エラーファイル数: {}", stats.error_files);
 
     if stats.processed_files > 0 {
         info!("平均圧縮率: {:.1}%", stats.get_compression_ratio() * 100.0);
+        info!("採用された平均品質: {:.1}", stats.get_average_quality());
         info!(
             "容量削減: {} → {} ({:.1}% 削減)",
             format_bytes(stats.original_size),
@@ -0,0 +1,153 @@
+//! BlurHashのエンコード実装
+//!
+//! 外部プロセスや専用クレートに頼らず、デコード済みの`DynamicImage`から直接
+//! BlurHash文字列を生成する。アルゴリズムは https://blurha.sh/ の仕様に準拠する。
+
+use anyhow::{bail, Result};
+use image::{DynamicImage, GenericImageView, RgbImage};
+
+const BASE83_CHARS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// 画像から指定した成分数でBlurHash文字列を生成する
+///
+/// `components_x` / `components_y` はいずれも1から9の範囲である必要がある。
+pub fn encode(img: &DynamicImage, components_x: u32, components_y: u32) -> Result<String> {
+    if !(1..=9).contains(&components_x) || !(1..=9).contains(&components_y) {
+        bail!("BlurHashの成分数は1から9の範囲である必要があります");
+    }
+
+    let (width, height) = img.dimensions();
+    let rgb = img.to_rgb8();
+
+    let mut factors = Vec::with_capacity((components_x * components_y) as usize);
+    for j in 0..components_y {
+        for i in 0..components_x {
+            let normalization = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            factors.push(multiply_basis_function(
+                &rgb,
+                width,
+                height,
+                i,
+                j,
+                normalization,
+            ));
+        }
+    }
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+
+    let mut result = String::new();
+
+    let size_flag = (components_x - 1) + (components_y - 1) * 9;
+    result.push_str(&encode_base83(size_flag, 1));
+
+    let maximum_value = if !ac.is_empty() {
+        let actual_max = ac
+            .iter()
+            .flat_map(|&(r, g, b)| [r.abs(), g.abs(), b.abs()])
+            .fold(0.0_f32, f32::max);
+        let quantized_max = (((actual_max * 166.0 - 0.5).floor().max(0.0)) as u32).min(82);
+        result.push_str(&encode_base83(quantized_max, 1));
+        (quantized_max as f32 + 1.0) / 166.0
+    } else {
+        result.push_str(&encode_base83(0, 1));
+        1.0
+    };
+
+    result.push_str(&encode_base83(encode_dc(dc), 4));
+
+    for &component in ac {
+        result.push_str(&encode_base83(encode_ac(component, maximum_value), 2));
+    }
+
+    Ok(result)
+}
+
+/// 基底関数 cos(πix/W)·cos(πjy/H) による係数を計算する
+///
+/// `normalization` はDC成分（i=0, j=0）では`1.0`、AC成分では`2.0`を渡す。
+/// 各ピクセルは逆sRGBガンマを適用し、リニアライトのRGBとして積算する。
+fn multiply_basis_function(
+    rgb: &RgbImage,
+    width: u32,
+    height: u32,
+    i: u32,
+    j: u32,
+    normalization: f32,
+) -> (f32, f32, f32) {
+    let mut r = 0.0_f64;
+    let mut g = 0.0_f64;
+    let mut b = 0.0_f64;
+
+    for y in 0..height {
+        for x in 0..width {
+            let basis = (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+            let pixel = rgb.get_pixel(x, y);
+            r += basis * srgb_to_linear(pixel[0]);
+            g += basis * srgb_to_linear(pixel[1]);
+            b += basis * srgb_to_linear(pixel[2]);
+        }
+    }
+
+    let scale = normalization as f64 / (width as f64 * height as f64);
+    ((r * scale) as f32, (g * scale) as f32, (b * scale) as f32)
+}
+
+/// DC係数をsRGBへ戻し、24ビット値（0xRRGGBB）へパックする
+fn encode_dc(value: (f32, f32, f32)) -> u32 {
+    let (r, g, b) = value;
+    (linear_to_srgb(r) as u32) << 16 | (linear_to_srgb(g) as u32) << 8 | linear_to_srgb(b) as u32
+}
+
+/// AC係数を`maximum_value`を基準に0..18へ量子化し、`(qR*19+qG)*19+qB`へパックする
+fn encode_ac(value: (f32, f32, f32), maximum_value: f32) -> u32 {
+    let quantize = |v: f32| -> u32 {
+        let v = signed_pow(v / maximum_value, 0.5);
+        ((v * 9.0 + 9.5).floor().clamp(0.0, 18.0)) as u32
+    };
+
+    let qr = quantize(value.0);
+    let qg = quantize(value.1);
+    let qb = quantize(value.2);
+
+    (qr * 19 + qg) * 19 + qb
+}
+
+/// 符号を保持したままべき乗する
+fn signed_pow(value: f32, exponent: f32) -> f32 {
+    value.abs().powf(exponent).copysign(value)
+}
+
+/// 逆sRGBガンマ（sRGB -> リニアライト）
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// sRGBガンマ（リニアライト -> sRGB）
+fn linear_to_srgb(value: f32) -> u8 {
+    let v = value.clamp(0.0, 1.0) as f64;
+    let srgb = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// 整数値を固定長のbase83文字列へエンコードする
+fn encode_base83(mut value: u32, length: usize) -> String {
+    let mut chars = vec![0_u8; length];
+    for slot in chars.iter_mut().rev() {
+        *slot = BASE83_CHARS[(value % 83) as usize];
+        value /= 83;
+    }
+    String::from_utf8(chars).expect("BASE83_CHARSはASCII文字のみで構成されている")
+}
@@ -0,0 +1,194 @@
+use clap::{Parser, ValueEnum};
+use std::fmt;
+use std::path::PathBuf;
+
+/// JPEG画像処理ユーティリティ
+#[derive(Parser, Debug)]
+#[command(author, version, about = "JPEG画像を処理するユーティリティ", long_about = None)]
+pub struct Cli {
+    /// 入力ディレクトリパス
+    #[arg(
+        long,
+        default_value = "../data/受領画像",
+        help = "処理するJPEGファイルが格納されているディレクトリのパスを指定します。"
+    )]
+    pub input_dir: PathBuf,
+
+    /// 出力ディレクトリパス
+    #[arg(
+        long,
+        default_value = "output/fixed_jpeg",
+        help = "出力ディレクトリのパスを指定します。"
+    )]
+    pub output_dir: PathBuf,
+
+    /// エンコード品質（1-100）。`--optimize` 指定時はこの値を中心にJPEG候補を探索し、
+    /// Rust内蔵経路でのWebP出力にも使う。既定経路のJPEG出力は常に品質100でエンコードし、この値は使わない
+    #[arg(long, default_value = "90", value_parser = quality_validator, help = "エンコード品質を指定します。--optimize 指定時のJPEG候補探索の基準品質、およびRust内蔵経路でのWebP出力に使用します（既定経路のJPEG出力は常に品質100）。1から100の範囲で指定してください。デフォルトは90です。")]
+    pub quality: u8,
+
+    /// 複数の候補エンコードを試し、正常にデコードできる中で最小の結果を採用する
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "品質・バックエンドなど複数の候補エンコードを並行して試し、正常にデコードできる中で最小の結果を採用します。"
+    )]
+    pub optimize: bool,
+
+    /// 1ファイルあたりの処理タイムアウト（秒）
+    #[arg(long, default_value = "30", value_parser = process_timeout_validator, help = "1ファイルの処理（外部ImageMagickプロセス・Rust内蔵フォールバックの両方）に許容する秒数を指定します。超過すると打ち切ってエラー扱いにします。デフォルトは30秒です。")]
+    pub process_timeout: u64,
+
+    /// 出力フォーマット（指定しない場合は各ファイルの入力拡張子を維持し、出力フォーマットにない拡張子はJPEGにする）
+    #[arg(
+        long,
+        value_enum,
+        help = "出力フォーマットを指定します。指定しない場合は入力ファイルごとの拡張子をそのまま維持します（jpg/png/webp/qoi/ppm以外の入力はJPEGで出力します）。"
+    )]
+    pub format: Option<OutputFormat>,
+
+    /// 各画像のBlurHashを計算し、出力ディレクトリ直下にJSONマニフェストとして書き出す
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "各画像のBlurHashプレースホルダー文字列を計算し、出力ディレクトリ直下にJSONマニフェストとして書き出します。"
+    )]
+    pub blurhash: bool,
+
+    /// BlurHashの成分数（`横x縦`、各1-9）
+    #[arg(
+        long,
+        default_value = "4x3",
+        value_parser = blurhash_components_validator,
+        help = "BlurHashの成分数を`横x縦`の形式で指定します（各1から9）。デフォルトは4x3です。"
+    )]
+    pub blurhash_components: (u32, u32),
+
+    /// 近似重複画像を事前に検出してスキップする
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "dHash（差分ハッシュ）による近似重複検出を有効にします。しきい値以内の画像は変換をスキップします。"
+    )]
+    pub dedup: bool,
+
+    /// 近似重複とみなすハミング距離のしきい値（0-64、小さいほど厳密）
+    #[arg(long, default_value = "5", value_parser = dedup_threshold_validator, help = "--dedup 指定時、dHashのハミング距離がこの値以下なら近似重複とみなしてスキップします。0から64の範囲で、デフォルトは5です。")]
+    pub dedup_threshold: u32,
+}
+
+/// 出力フォーマットの種類
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// imageクレート（または利用可能ならmozjpeg）によるJPEG出力
+    Jpg,
+    /// imageクレートによるロスレスPNG出力
+    Png,
+    /// WebPエンコーダー（要 webp-encoder フィーチャー）
+    Webp,
+    /// QOI（Quite OK Image）出力
+    Qoi,
+    /// PPM（imageクレートのPNM実装）出力
+    Ppm,
+}
+
+impl OutputFormat {
+    /// この出力フォーマットのファイル拡張子（ドットなし）
+    ///
+    /// `webp-encoder` フィーチャーが無効な場合、`Webp` のエンコードはJPEGへ
+    /// フォールバックするため（`encode_webp` 参照）、拡張子もそれに合わせてJPEGを返す。
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpg => "jpg",
+            OutputFormat::Png => "png",
+            #[cfg(feature = "webp-encoder")]
+            OutputFormat::Webp => "webp",
+            #[cfg(not(feature = "webp-encoder"))]
+            OutputFormat::Webp => "jpg",
+            OutputFormat::Qoi => "qoi",
+            OutputFormat::Ppm => "ppm",
+        }
+    }
+
+    /// ファイル拡張子から対応する出力フォーマットを推測する
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(OutputFormat::Jpg),
+            "png" => Some(OutputFormat::Png),
+            "webp" => Some(OutputFormat::Webp),
+            "qoi" => Some(OutputFormat::Qoi),
+            "ppm" => Some(OutputFormat::Ppm),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// コマンドライン引数をパースする
+pub fn parse_args() -> Cli {
+    Cli::parse()
+}
+
+/// 品質パラメータのバリデーション（1-100の範囲内であることを確認）
+fn quality_validator(s: &str) -> Result<u8, String> {
+    s.parse::<u8>()
+        .map_err(|_| format!("`{}` は有効な数値ではありません", s))
+        .and_then(|quality| {
+            if (1..=100).contains(&quality) {
+                Ok(quality)
+            } else {
+                Err("品質は1から100の間である必要があります".to_string())
+            }
+        })
+}
+
+/// タイムアウト秒数のバリデーション（1秒以上であることを確認）
+fn process_timeout_validator(s: &str) -> Result<u64, String> {
+    s.parse::<u64>()
+        .map_err(|_| format!("`{}` は有効な数値ではありません", s))
+        .and_then(|timeout| {
+            if timeout >= 1 {
+                Ok(timeout)
+            } else {
+                Err("タイムアウトは1秒以上である必要があります".to_string())
+            }
+        })
+}
+
+/// BlurHash成分数のバリデーション（`横x縦`形式、各1-9の範囲であることを確認）
+fn blurhash_components_validator(s: &str) -> Result<(u32, u32), String> {
+    let (x_str, y_str) = s
+        .split_once('x')
+        .ok_or_else(|| format!("`{}` は `横x縦` の形式で指定してください（例: 4x3）", s))?;
+
+    let x = x_str
+        .parse::<u32>()
+        .map_err(|_| format!("`{}` は有効な数値ではありません", x_str))?;
+    let y = y_str
+        .parse::<u32>()
+        .map_err(|_| format!("`{}` は有効な数値ではありません", y_str))?;
+
+    if !(1..=9).contains(&x) || !(1..=9).contains(&y) {
+        return Err("BlurHashの成分数は1から9の間である必要があります".to_string());
+    }
+
+    Ok((x, y))
+}
+
+/// 近似重複しきい値のバリデーション（0-64の範囲内であることを確認）
+fn dedup_threshold_validator(s: &str) -> Result<u32, String> {
+    s.parse::<u32>()
+        .map_err(|_| format!("`{}` は有効な数値ではありません", s))
+        .and_then(|threshold| {
+            if threshold <= 64 {
+                Ok(threshold)
+            } else {
+                Err("しきい値は0から64の間である必要があります".to_string())
+            }
+        })
+}
@@ -0,0 +1,38 @@
+//! dHash（差分ハッシュ）による近似知覚ハッシュの実装
+//!
+//! グレースケールへ変換後 9x8 にダウンスケールし、各行で隣接ピクセルの大小関係を
+//! 64ビットへパックする。2つの画像が近似複製かどうかは、両ハッシュをXORした結果の
+//! 立っているビット数（ハミング距離）がしきい値以下かどうかで判定する。
+
+use image::{imageops::FilterType, DynamicImage};
+
+/// ダウンスケール先の幅（1行あたり9ピクセル → 隣接比較8ビット）
+const HASH_WIDTH: u32 = 9;
+/// ダウンスケール先の高さ（8行 × 8ビット = 64ビット）
+const HASH_HEIGHT: u32 = 8;
+
+/// 画像から64ビットのdHashを計算する
+pub fn compute(img: &DynamicImage) -> u64 {
+    let small = img
+        .resize_exact(HASH_WIDTH, HASH_HEIGHT, FilterType::Triangle)
+        .to_luma8();
+
+    let mut hash: u64 = 0;
+    for y in 0..HASH_HEIGHT {
+        for x in 0..HASH_WIDTH - 1 {
+            hash <<= 1;
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            if left > right {
+                hash |= 1;
+            }
+        }
+    }
+
+    hash
+}
+
+/// 2つのハッシュ間のハミング距離（異なるビット数）を計算する
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
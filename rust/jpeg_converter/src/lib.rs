@@ -3,8 +3,14 @@
 //! 指定されたディレクトリ内のJPEG画像を処理し、
 //! ディレクトリ構造を維持しながら出力します。
 
-// 必要に応じてモジュールを追加できます
-// pub mod example;
+pub mod blurhash;
+pub mod cli;
+pub mod dhash;
+pub mod logger;
+pub mod processor;
+
+pub use cli::Cli;
+pub use processor::process_images;
 
 /// プレースホルダー関数
 pub fn version() -> &'static str {
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use log::info;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// パッケージ名をjpeg_converterに変更
+use jpeg_converter::{cli, logger, process_images};
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("エラー: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    // コマンドライン引数をパース
+    let cli = cli::parse_args();
+
+    // 入力・出力ディレクトリのパスを解決
+    let current_dir = std::env::current_dir()?;
+    let input_dir = resolve_path(&cli.input_dir, &current_dir);
+    let output_dir = resolve_path(&cli.output_dir, &current_dir);
+
+    // ロギングとプログレスバーを設定（ファイル数はprocess_images内で確定する）
+    logger::setup_logging_and_progress(0)?;
+
+    info!("JPEG画像処理ユーティリティを開始します");
+    info!("入力ディレクトリ: {}", input_dir.display());
+    info!("出力ディレクトリ: {}", output_dir.display());
+    if cli.optimize {
+        info!(
+            "最適化モード: 複数候補から最小のエンコード結果を採用します（基準品質 {}）",
+            cli.quality
+        );
+    }
+    if let Some(format) = cli.format {
+        info!("出力フォーマット: {} に統一します", format);
+    }
+    if cli.blurhash {
+        info!(
+            "BlurHashマニフェストを生成します（成分数 {}x{}）",
+            cli.blurhash_components.0, cli.blurhash_components.1
+        );
+    }
+    if cli.dedup {
+        info!("近似重複検出: 有効（しきい値 {}）", cli.dedup_threshold);
+    }
+
+    let start_time = Instant::now();
+
+    let stats = process_images(
+        &input_dir,
+        &output_dir,
+        cli.quality,
+        cli.optimize,
+        Duration::from_secs(cli.process_timeout),
+        cli.format,
+        cli.blurhash,
+        cli.blurhash_components,
+        cli.dedup,
+        cli.dedup_threshold,
+    )?;
+
+    let elapsed = start_time.elapsed();
+    info!(
+        "処理が完了しました。所要時間: {:.2}秒",
+        elapsed.as_secs_f64()
+    );
+    info!("処理ファイル数: {}", stats.processed_files);
+    info!("近似重複スキップ数: {}", stats.duplicate_files);
+    info!("エラーファイル数: {}", stats.error_files);
+
+    if stats.processed_files > 0 {
+        info!(
+            "容量: {} → {}",
+            format_bytes(stats.original_size),
+            format_bytes(stats.compressed_size)
+        );
+        if cli.optimize && stats.optimize_savings > 0 {
+            info!(
+                "最適化トライアルによる追加削減量: {}",
+                format_bytes(stats.optimize_savings)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// 相対パスまたは絶対パスを適切に解決する
+fn resolve_path(path: &Path, current_dir: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        current_dir.join(path)
+    }
+}
+
+/// バイト数を人間が読みやすい形式にフォーマットする
+fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
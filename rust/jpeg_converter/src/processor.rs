@@ -1,18 +1,71 @@
+use crate::blurhash;
+use crate::cli::OutputFormat;
+use crate::dhash;
 use crate::logger;
 use anyhow::{Context, Result};
+use image::DynamicImage;
+use image::GenericImageView;
+use image::codecs::jpeg::JpegEncoder;
+use image::io::Reader as ImageReader;
 use log::{info, warn};
 use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::fs;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
-/// 外部コマンドを使ったJPEGファイル処理
-pub fn process_images(input_dir: &Path, output_dir: &Path) -> Result<()> {
+/// 処理結果の統計情報
+#[derive(Debug, Default)]
+pub struct ProcessStats {
+    pub processed_files: usize,
+    pub error_files: usize,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    /// `--optimize` 指定時、複数候補を試したことで採用された基準エンコードより
+    /// 追加で削減できたバイト数の合計（未指定時は常に0）
+    pub optimize_savings: u64,
+    /// `--dedup` 指定時、近似重複として変換をスキップしたファイル数
+    pub duplicate_files: usize,
+}
+
+/// 1ファイル分のエンコード結果
+struct EncodeOutcome {
+    original_size: u64,
+    compressed_size: u64,
+    optimize_savings: u64,
+    /// 処理中にRustでデコードした画像（呼び出し元がBlurHash計算に再利用できる）
+    ///
+    /// ImageMagick経由で処理し、呼び出し元からデコード済み画像も渡されていない場合は`None`になる。
+    decoded_image: Option<DynamicImage>,
+}
+
+/// BlurHashマニフェストへ書き出す1ファイル分のエントリ
+#[derive(Debug, Serialize)]
+struct BlurhashEntry {
+    hash: String,
+    width: u32,
+    height: u32,
+}
+
+/// 画像ファイルの処理（外部コマンドまたはRust内蔵機能を使用）
+pub fn process_images(
+    input_dir: &Path,
+    output_dir: &Path,
+    quality: u8,
+    optimize: bool,
+    process_timeout: Duration,
+    format: Option<OutputFormat>,
+    blurhash: bool,
+    blurhash_components: (u32, u32),
+    dedup: bool,
+    dedup_threshold: u32,
+) -> Result<ProcessStats> {
     // 画像処理ツールの存在チェック
     check_external_tools()?;
 
@@ -28,7 +81,7 @@ pub fn process_images(input_dir: &Path, output_dir: &Path) -> Result<()> {
     fs::create_dir_all(output_dir)?;
 
     // 処理対象のファイル一覧を取得
-    let files: Vec<_> = collect_jpeg_files(input_dir);
+    let files: Vec<_> = collect_image_files(input_dir);
     let total_files = files.len();
 
     info!("合計 {} ファイルを処理します", total_files);
@@ -39,9 +92,15 @@ pub fn process_images(input_dir: &Path, output_dir: &Path) -> Result<()> {
     progress_bar.set_message("画像処理中...");
     progress_bar.enable_steady_tick(Duration::from_millis(200));
 
-    // 処理成功/失敗のカウンター
-    let success_count = Arc::new(AtomicUsize::new(0));
-    let error_count = Arc::new(AtomicUsize::new(0));
+    // 処理成功/失敗のカウンターと統計情報
+    let success_count = AtomicUsize::new(0);
+    let error_count = AtomicUsize::new(0);
+    let original_size_total = AtomicU64::new(0);
+    let compressed_size_total = AtomicU64::new(0);
+    let optimize_savings_total = AtomicU64::new(0);
+    let duplicate_count = AtomicUsize::new(0);
+    let blurhash_manifest: Mutex<HashMap<String, BlurhashEntry>> = Mutex::new(HashMap::new());
+    let seen_hashes: Mutex<Vec<(u64, PathBuf)>> = Mutex::new(Vec::new());
 
     // スレッド数を制限（コア数の1/2を使用）
     let num_threads = std::cmp::max(1, num_cpus::get() / 2);
@@ -64,7 +123,10 @@ pub fn process_images(input_dir: &Path, output_dir: &Path) -> Result<()> {
             }
         };
 
-        let output_file = output_dir.join(relative_path);
+        // 出力フォーマットは --format が優先、指定がなければ入力ファイルの拡張子を維持する（推測できなければJPEG）
+        let output_format = resolve_output_format(format, relative_path);
+        let relative_output_path = relative_path.with_extension(output_format.extension());
+        let output_file = output_dir.join(&relative_output_path);
 
         // 出力先ディレクトリを作成
         if let Some(parent) = output_file.parent() {
@@ -75,9 +137,50 @@ pub fn process_images(input_dir: &Path, output_dir: &Path) -> Result<()> {
             }
         }
 
+        // 近似重複検出（dHash） - しきい値以内の既知ハッシュが見つかればスキップする
+        // デコード済みの画像は後段の変換処理・BlurHash計算でも使い回し、二重にデコードしない
+        let mut decoded_image: Option<DynamicImage> = None;
+        if dedup {
+            match decode_image(file_path) {
+                Ok(img) => {
+                    let hash = dhash::compute(&img);
+                    let mut seen = seen_hashes.lock().unwrap();
+
+                    if let Some((_, duplicate_of)) = seen
+                        .iter()
+                        .find(|(seen_hash, _)| dhash::hamming_distance(*seen_hash, hash) <= dedup_threshold)
+                    {
+                        info!(
+                            "近似重複のためスキップ: {} ({} の重複)",
+                            relative_path.display(),
+                            duplicate_of.display()
+                        );
+                        drop(seen);
+                        duplicate_count.fetch_add(1, Ordering::SeqCst);
+                        progress_bar.inc(1);
+                        return;
+                    }
+
+                    seen.push((hash, relative_path.to_path_buf()));
+                    decoded_image = Some(img);
+                }
+                Err(e) => {
+                    warn!("重複検出用のデコードに失敗しました: {} - {}", file_path.display(), e);
+                }
+            }
+        }
+
         let start_time = Instant::now();
-        match process_jpeg_file(file_path, &output_file) {
-            Ok(_) => {
+        match process_jpeg_file(
+            file_path,
+            &output_file,
+            quality,
+            optimize,
+            process_timeout,
+            output_format,
+            decoded_image,
+        ) {
+            Ok(outcome) => {
                 let elapsed = start_time.elapsed();
                 if elapsed.as_secs() > 5 {
                     warn!(
@@ -87,6 +190,20 @@ pub fn process_images(input_dir: &Path, output_dir: &Path) -> Result<()> {
                     );
                 }
 
+                original_size_total.fetch_add(outcome.original_size, Ordering::Relaxed);
+                compressed_size_total.fetch_add(outcome.compressed_size, Ordering::Relaxed);
+                optimize_savings_total.fetch_add(outcome.optimize_savings, Ordering::Relaxed);
+
+                if blurhash {
+                    record_blurhash(
+                        file_path,
+                        relative_path,
+                        blurhash_components,
+                        &blurhash_manifest,
+                        outcome.decoded_image,
+                    );
+                }
+
                 let count = success_count.fetch_add(1, Ordering::SeqCst) + 1;
                 if count % 10 == 0 {
                     info!("成功: {}/{} ファイル処理済み", count, total_files);
@@ -111,25 +228,119 @@ pub fn process_images(input_dir: &Path, output_dir: &Path) -> Result<()> {
     progress_bar.finish_with_message(format!("処理完了：成功 {}, 失敗 {}", success, errors));
     info!("処理結果: 成功 {}, 失敗 {}", success, errors);
 
-    Ok(())
+    if blurhash {
+        write_blurhash_manifest(output_dir, blurhash_manifest)?;
+    }
+
+    Ok(ProcessStats {
+        processed_files: success,
+        error_files: errors,
+        original_size: original_size_total.load(Ordering::SeqCst),
+        compressed_size: compressed_size_total.load(Ordering::SeqCst),
+        optimize_savings: optimize_savings_total.load(Ordering::SeqCst),
+        duplicate_files: duplicate_count.load(Ordering::SeqCst),
+    })
 }
 
-/// JPEGファイルをリストアップする
-fn collect_jpeg_files(dir: &Path) -> Vec<PathBuf> {
+/// 処理対象の画像ファイルをリストアップする（JPEG以外のデコード可能な入力も含む）
+fn collect_image_files(dir: &Path) -> Vec<PathBuf> {
     WalkDir::new(dir)
         .into_iter()
         .filter_map(Result::ok)
         .filter(|e| e.file_type().is_file())
         .map(|e| e.path().to_path_buf())
         .filter(|p| {
+            let ext_lower = p
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase())
+                .unwrap_or_default();
             matches!(
-                p.extension().and_then(|e| e.to_str()),
-                Some("jpg" | "jpeg" | "JPG" | "JPEG")
+                ext_lower.as_str(),
+                "jpg" | "jpeg" | "png" | "webp" | "bmp" | "tiff" | "gif"
+                    | "heic" | "heif"
+                    | "cr2" | "nef" | "arw" | "dng"
             )
         })
         .collect()
 }
 
+/// 各ファイルの出力フォーマットを決定する
+///
+/// `--format` が指定されていればそれを優先し、ディレクトリ全体を同一フォーマットへ
+/// 変換する。指定がなければ入力ファイルの拡張子から推測し、各ファイルの元の
+/// フォーマットを維持する（推測できない場合はJPEGにフォールバックする）。
+fn resolve_output_format(format: Option<OutputFormat>, relative_path: &Path) -> OutputFormat {
+    if let Some(format) = format {
+        return format;
+    }
+
+    relative_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(OutputFormat::from_extension)
+        .unwrap_or(OutputFormat::Jpg)
+}
+
+/// 1ファイル分のBlurHashを計算し、マニフェストへ登録する
+///
+/// エンコード済みの出力ではなく元画像から直接計算する。`preloaded` に変換処理で
+/// 既にデコード済みの画像が渡されていればそれを再利用し、なければ改めてデコードする。
+/// 失敗しても処理全体は止めず、警告を出して当該ファイルをマニフェストから除外する。
+fn record_blurhash(
+    file_path: &Path,
+    relative_path: &Path,
+    blurhash_components: (u32, u32),
+    blurhash_manifest: &Mutex<HashMap<String, BlurhashEntry>>,
+    preloaded: Option<DynamicImage>,
+) {
+    let img = match preloaded {
+        Some(img) => img,
+        None => match decode_image(file_path) {
+            Ok(img) => img,
+            Err(e) => {
+                warn!("BlurHash用のデコードに失敗しました: {} - {}", file_path.display(), e);
+                return;
+            }
+        },
+    };
+
+    let (width, height) = img.dimensions();
+    match blurhash::encode(&img, blurhash_components.0, blurhash_components.1) {
+        Ok(hash) => {
+            let key = relative_path.to_string_lossy().replace('\\', "/");
+            if let Ok(mut manifest) = blurhash_manifest.lock() {
+                manifest.insert(key, BlurhashEntry { hash, width, height });
+            }
+        }
+        Err(e) => warn!("BlurHash生成に失敗しました: {} - {}", file_path.display(), e),
+    }
+}
+
+/// BlurHashマニフェストをJSONとして出力ディレクトリ直下に書き出す
+fn write_blurhash_manifest(
+    output_dir: &Path,
+    blurhash_manifest: Mutex<HashMap<String, BlurhashEntry>>,
+) -> Result<()> {
+    let manifest = blurhash_manifest
+        .into_inner()
+        .with_context(|| "BlurHashマニフェストのロック解除に失敗しました")?;
+
+    let manifest_path = output_dir.join("blurhash_manifest.json");
+    let json = serde_json::to_string_pretty(&manifest)
+        .with_context(|| "BlurHashマニフェストのシリアライズに失敗しました")?;
+
+    fs::write(&manifest_path, json).with_context(|| {
+        format!(
+            "BlurHashマニフェストの書き込みに失敗しました: {}",
+            manifest_path.display()
+        )
+    })?;
+
+    info!("BlurHashマニフェストを書き出しました: {}", manifest_path.display());
+    Ok(())
+}
+
 /// 外部コマンドが利用可能かチェック
 fn check_external_tools() -> Result<()> {
     // まずImageMagickを確認
@@ -151,81 +362,545 @@ fn check_external_tools() -> Result<()> {
     Ok(())
 }
 
-/// 画像変換処理を実行する（Python実装に合わせた処理）
-fn process_jpeg_file(input_path: &Path, output_path: &Path) -> Result<()> {
-    // ImageMagickが使えるかチェック（最も一般的）
-    if Command::new("convert")
+/// 入力がImageMagickの標準デリゲートでは扱えず、Rustの専用デコーダーが必要な形式かどうか
+///
+/// HEIF/HEIC・カメラRAWはImageMagickにHEIF/RAWデリゲートが入っていない環境が大半のため、
+/// `decode_heif`/`decode_raw`（`decode_image` 経由）を必ず通すようにする。
+fn requires_rust_decoder(input_path: &Path) -> bool {
+    let ext_lower = input_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    matches!(
+        ext_lower.as_str(),
+        "heic" | "heif" | "cr2" | "nef" | "arw" | "dng"
+    )
+}
+
+/// 画像変換処理を実行する
+///
+/// `optimize` が有効な場合、出力がJPEGなら複数の候補エンコードを試して最小の結果を
+/// 採用し、JPEG以外ならRust内蔵のエンコーダーで単一経路の変換を行う。`optimize` が
+/// 無効な場合、出力がQOI以外かつ入力がHEIF/HEIC・カメラRAWでなければ
+/// ImageMagick（無損失）を優先し、利用できなければRust内蔵の
+/// imageクレートにフォールバックする。QOI出力、およびHEIF/HEIC・RAW入力は
+/// ImageMagickの標準デリゲートでは扱えないため常にRust側のデコーダー/エンコーダーを使用する。
+/// いずれの経路も `process_timeout` を超えたら打ち切り、壊れた1ファイルが
+/// ディレクトリ全体の処理を止めないようにする。
+///
+/// `preloaded` に呼び出し元（`--dedup` の重複検出）で既にデコード済みの画像が
+/// 渡されていれば、Rust側のデコード経路ではそれを再利用し重複デコードを避ける。
+fn process_jpeg_file(
+    input_path: &Path,
+    output_path: &Path,
+    quality: u8,
+    optimize: bool,
+    process_timeout: Duration,
+    output_format: OutputFormat,
+    preloaded: Option<DynamicImage>,
+) -> Result<EncodeOutcome> {
+    let original_size = fs::metadata(input_path).map(|m| m.len()).unwrap_or(0);
+
+    if optimize {
+        let input_path_owned = input_path.to_path_buf();
+        let output_path_owned = output_path.to_path_buf();
+        let (winner, optimize_savings, decoded_image) =
+            run_with_timeout(process_timeout, move || {
+                let img = match preloaded {
+                    Some(img) => img,
+                    None => decode_image(&input_path_owned)?,
+                };
+
+                // 複数候補の比較が意味を持つのはJPEGのみ（他フォーマットは通常の単一経路）
+                if output_format == OutputFormat::Jpg {
+                    let baseline = encode_with_image_crate(&img, quality).with_context(|| {
+                        format!("基準エンコードに失敗しました: {}", input_path_owned.display())
+                    })?;
+                    let winner = optimize_jpeg_file(&img, &output_path_owned, quality)?;
+                    let savings = baseline.len().saturating_sub(winner.len()) as u64;
+                    Ok((winner, savings, img))
+                } else {
+                    let winner = encode_for_format(&img, output_format, quality)?;
+                    Ok((winner, 0, img))
+                }
+            })?;
+
+        fs::write(output_path, &winner).with_context(|| {
+            format!(
+                "出力ファイルを作成できませんでした: {}",
+                output_path.display()
+            )
+        })?;
+
+        return Ok(EncodeOutcome {
+            original_size,
+            compressed_size: winner.len() as u64,
+            optimize_savings,
+            decoded_image: Some(decoded_image),
+        });
+    }
+
+    let imagemagick_available = Command::new("convert")
         .arg("-version")
         .stdout(Stdio::null())
         .status()
-        .is_ok()
-    {
-        // ImageMagickで処理（無損失処理）
-        let status = Command::new("convert")
+        .is_ok();
+
+    let mut decoded_image: Option<DynamicImage> = None;
+
+    if output_format != OutputFormat::Qoi && !requires_rust_decoder(input_path) && imagemagick_available {
+        // ImageMagickで処理（出力ファイルの拡張子からフォーマットを推測させる）
+        // タイムアウトを超えたら強制終了する。ここではRust側でデコードしないため、
+        // `decoded_image`には呼び出し元から渡されたデコード済み画像（なければ`None`）を引き継ぐ。
+        decoded_image = preloaded;
+        let mut command = Command::new("convert");
+        command
             .arg(input_path)
             .arg("-quality")
-            .arg("100") // 無損失でJPEGを出力 (py_jpeg_processorと同じ)
-            .arg(output_path)
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .with_context(|| {
-                format!("ImageMagickの実行に失敗しました: {}", input_path.display())
-            })?;
+            .arg("100") // 無損失/最高品質で出力 (py_jpeg_processorと同じ)
+            .arg(output_path);
 
-        if status.success() {
-            return Ok(());
-        } else {
+        let status = run_command_with_timeout(command, process_timeout)
+            .with_context(|| format!("ImageMagickの実行に失敗しました: {}", input_path.display()))?;
+
+        if !status.success() {
             return Err(anyhow::anyhow!(
                 "ImageMagickがエラーコード {} で終了しました",
                 status
             ));
         }
+    } else {
+        // ImageMagickが使えない、QOI出力、またはHEIF/HEIC・RAW入力の場合はRust内蔵機能を使用
+        let input_path_owned = input_path.to_path_buf();
+        let output_path_owned = output_path.to_path_buf();
+        decoded_image = Some(run_with_timeout(process_timeout, move || {
+            process_with_rust_image(preloaded, &input_path_owned, &output_path_owned, output_format, quality)
+        })?);
     }
 
-    // どちらも使えない場合はRust内蔵機能を使用
-    process_with_rust_image(input_path, output_path)
+    let compressed_size = fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+
+    Ok(EncodeOutcome {
+        original_size,
+        compressed_size,
+        optimize_savings: 0,
+        decoded_image,
+    })
 }
 
-/// Rustのimage crateを使ってJPEG画像を処理
-fn process_with_rust_image(input_path: &Path, output_path: &Path) -> Result<()> {
-    use image::codecs::jpeg::JpegEncoder;
-    use image::io::Reader as ImageReader;
+/// 外部コマンドを起動し、`timeout` を超えたら強制終了する
+///
+/// `try_wait` による短い間隔でのポーリングで完了を待つ。タイムアウトに達した場合は
+/// 子プロセスをkillし、そのまま打ち切りエラーとして返す。
+fn run_command_with_timeout(
+    mut command: Command,
+    timeout: Duration,
+) -> Result<std::process::ExitStatus> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| "サブプロセスの起動に失敗しました")?;
 
-    let img = ImageReader::open(input_path)
-        .with_context(|| format!("ファイルを開けませんでした: {}", input_path.display()))?
-        .with_guessed_format()
-        .with_context(|| "フォーマット推測に失敗しました")?
-        .decode()
-        .with_context(|| format!("画像デコードに失敗しました: {}", input_path.display()))?;
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child
+            .try_wait()
+            .with_context(|| "サブプロセスの状態取得に失敗しました")?
+        {
+            return Ok(status);
+        }
 
-    // 検出された形式を記録
-    let format = match ImageReader::open(input_path)?.with_guessed_format()? {
-        reader => match reader.format() {
-            Some(fmt) => format!("{:?}", fmt),
-            None => "不明".to_string(),
-        },
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow::anyhow!(
+                "処理がタイムアウトしました（{}秒）のため強制終了しました",
+                timeout.as_secs()
+            ));
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// 重い処理を別スレッドで実行し、`timeout` を超えたら結果を待たずに打ち切る
+///
+/// スレッドそのものを強制終了することはできないため、あくまで「結果を待たずに
+/// 諦める」ことで呼び出し元（ディレクトリ全体の走査）を止めないようにする。
+fn run_with_timeout<F, T>(timeout: Duration, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(f());
+    });
+
+    rx.recv_timeout(timeout).unwrap_or_else(|_| {
+        Err(anyhow::anyhow!(
+            "処理がタイムアウトしました（{}秒）",
+            timeout.as_secs()
+        ))
+    })
+}
+
+/// 画像ファイルをデコードする
+///
+/// HEIF/HEIC・カメラRAW（CR2/NEF/ARW/DNG）は拡張子で判定し、それぞれ専用の
+/// デコーダーでRGBバッファへ変換してから`DynamicImage`に載せる。それ以外は
+/// 従来どおりimageクレートにフォーマットを推測させて読み込む。
+fn decode_image(input_path: &Path) -> Result<DynamicImage> {
+    let ext_lower = input_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    match ext_lower.as_str() {
+        "heic" | "heif" => decode_heif(input_path),
+        "cr2" | "nef" | "arw" | "dng" => decode_raw(input_path),
+        _ => ImageReader::open(input_path)
+            .with_context(|| format!("ファイルを開けませんでした: {}", input_path.display()))?
+            .with_guessed_format()
+            .with_context(|| "フォーマット推測に失敗しました")?
+            .decode()
+            .with_context(|| format!("画像デコードに失敗しました: {}", input_path.display())),
+    }
+}
+
+/// libheif-rsを使用したHEIF/HEICデコード実装
+#[cfg(feature = "heif-decoder")]
+fn decode_heif(input_path: &Path) -> Result<DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, RgbChroma};
+
+    let ctx = HeifContext::read_from_file(&input_path.to_string_lossy())
+        .with_context(|| format!("HEIFファイルを開けませんでした: {}", input_path.display()))?;
+    let handle = ctx
+        .primary_image_handle()
+        .with_context(|| format!("HEIFの主画像取得に失敗しました: {}", input_path.display()))?;
+    let image = handle
+        .decode(ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .with_context(|| format!("HEIFデコードに失敗しました: {}", input_path.display()))?;
+
+    let width = image.width();
+    let height = image.height();
+    let planes = image.planes();
+    let plane = planes
+        .interleaved
+        .with_context(|| "HEIFのRGBプレーン取得に失敗しました")?;
+
+    let mut buffer = image::RgbImage::new(width, height);
+    for y in 0..height {
+        let row_start = (y as usize) * plane.stride;
+        for x in 0..width {
+            let i = row_start + (x as usize) * 3;
+            buffer.put_pixel(
+                x,
+                y,
+                image::Rgb([plane.data[i], plane.data[i + 1], plane.data[i + 2]]),
+            );
+        }
+    }
+
+    Ok(DynamicImage::ImageRgb8(buffer))
+}
+
+/// HEIF/HEICデコーダーが利用できない場合の代替実装
+#[cfg(not(feature = "heif-decoder"))]
+fn decode_heif(input_path: &Path) -> Result<DynamicImage> {
+    Err(anyhow::anyhow!(
+        "heif-decoder フィーチャーが無効なためHEIF/HEICを読み込めません: {}",
+        input_path.display()
+    ))
+}
+
+/// rawloader + imagepipeを使用したカメラRAWデコード実装
+#[cfg(feature = "raw-decoder")]
+fn decode_raw(input_path: &Path) -> Result<DynamicImage> {
+    let raw_image = rawloader::decode_file(input_path)
+        .with_context(|| format!("RAWファイルのデコードに失敗しました: {}", input_path.display()))?;
+
+    let developed = imagepipe::Pipeline::new_from_raw(raw_image)
+        .with_context(|| "現像パイプラインの構築に失敗しました")?
+        .output_8bit(None)
+        .with_context(|| format!("RAW現像に失敗しました: {}", input_path.display()))?;
+
+    let buffer = image::RgbImage::from_raw(
+        developed.width as u32,
+        developed.height as u32,
+        developed.data,
+    )
+    .with_context(|| "現像結果からのRGBバッファ構築に失敗しました")?;
+
+    Ok(DynamicImage::ImageRgb8(buffer))
+}
+
+/// RAWデコーダーが利用できない場合の代替実装
+#[cfg(not(feature = "raw-decoder"))]
+fn decode_raw(input_path: &Path) -> Result<DynamicImage> {
+    Err(anyhow::anyhow!(
+        "raw-decoder フィーチャーが無効なためRAWファイルを読み込めません: {}",
+        input_path.display()
+    ))
+}
+
+/// Rustのimage crateを使って画像を処理する（`output_format` に応じたフォーマットへ変換）
+///
+/// `preloaded` が渡されていればデコードを省略して再利用する。呼び出し元がBlurHash計算
+/// などで再デコードせずに済むよう、処理に使ったデコード済み画像を返す。
+fn process_with_rust_image(
+    preloaded: Option<DynamicImage>,
+    input_path: &Path,
+    output_path: &Path,
+    output_format: OutputFormat,
+    quality: u8,
+) -> Result<DynamicImage> {
+    let img = match preloaded {
+        Some(img) => img,
+        None => decode_image(input_path)?,
     };
-    info!(
-        "ファイル: {}, 検出された形式: {}",
-        input_path.display(),
-        format
-    );
 
-    let output_file = fs::File::create(output_path).with_context(|| {
+    // JPEG以外はpy_jpeg_processorの慣習に合わせず、`quality` をそのままエンコーダーへ渡す
+    // （品質を持たないPNG/PPM/QOIでは無視される）
+    let quality = if output_format == OutputFormat::Jpg {
+        100 // py_jpeg_processor と同様に最高品質設定を使用
+    } else {
+        quality
+    };
+
+    let encoded = encode_for_format(&img, output_format, quality)?;
+    fs::write(output_path, &encoded).with_context(|| {
         format!(
             "出力ファイルを作成できませんでした: {}",
             output_path.display()
         )
     })?;
 
-    // py_jpeg_processor と同様に最高品質設定を使用
-    let mut encoder = JpegEncoder::new_with_quality(std::io::BufWriter::new(output_file), 100);
+    info!(
+        "{} として保存しました: {}",
+        output_format,
+        output_path.display()
+    );
+    Ok(img)
+}
+
+/// 指定された出力フォーマットへ画像をエンコードする
+fn encode_for_format(img: &DynamicImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>> {
+    match format {
+        OutputFormat::Jpg => encode_with_image_crate(img, quality),
+        OutputFormat::Png => encode_with_image_format(img, image::ImageFormat::Png),
+        OutputFormat::Ppm => encode_with_image_format(img, image::ImageFormat::Pnm),
+        OutputFormat::Webp => encode_webp(img, quality),
+        OutputFormat::Qoi => encode_qoi(img),
+    }
+}
+
+/// imageクレート標準のエンコーダーを経由した汎用エンコード実装（PNG/PPMなど）
+fn encode_with_image_format(img: &DynamicImage, format: image::ImageFormat) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buffer), format)
+        .with_context(|| format!("{:?}へのエンコードに失敗しました", format))?;
+    Ok(buffer)
+}
+
+/// WebPエンコード実装（`webp-encoder` フィーチャーが無効な場合はJPEGへフォールバックする）
+#[cfg(feature = "webp-encoder")]
+fn encode_webp(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let encoder = webp::Encoder::from_rgb(&rgb_img, rgb_img.width(), rgb_img.height());
+    let encoded = encoder.encode(quality as f32);
+    Ok(encoded.to_vec())
+}
+
+#[cfg(not(feature = "webp-encoder"))]
+fn encode_webp(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    warn!("webp-encoder フィーチャーが無効なため、WebPの代わりにJPEGでエンコードします");
+    encode_with_image_crate(img, quality)
+}
+
+/// QOI（Quite OK Image）エンコード実装
+fn encode_qoi(img: &DynamicImage) -> Result<Vec<u8>> {
+    let rgba_img = img.to_rgba8();
+    qoi::encode_to_vec(&rgba_img, rgba_img.width(), rgba_img.height())
+        .with_context(|| "QOIエンコードに失敗しました")
+}
+
+/// `--optimize` で試す1候補（バックエンド・品質の組み合わせ）
+struct Candidate {
+    label: &'static str,
+    quality: u8,
+    encode: fn(&DynamicImage, u8) -> Result<Vec<u8>>,
+}
+
+/// 基準品質の前後・利用可能な各バックエンドから候補エンコードの一覧を組み立てる
+fn build_candidates(base_quality: u8) -> Vec<Candidate> {
+    let qualities = [
+        base_quality.saturating_sub(5).max(1),
+        base_quality,
+        base_quality.saturating_add(5).min(100),
+    ];
+
+    let mut candidates = Vec::new();
+    for &quality in &qualities {
+        candidates.push(Candidate {
+            label: "image",
+            quality,
+            encode: encode_with_image_crate,
+        });
+    }
+
+    #[cfg(feature = "mozjpeg-encoder")]
+    for &quality in &qualities {
+        candidates.push(Candidate {
+            label: "mozjpeg-baseline",
+            quality,
+            encode: encode_with_mozjpeg_baseline,
+        });
+        candidates.push(Candidate {
+            label: "mozjpeg-baseline-420",
+            quality,
+            encode: encode_with_mozjpeg_baseline_subsampled,
+        });
+        candidates.push(Candidate {
+            label: "mozjpeg-progressive",
+            quality,
+            encode: encode_with_mozjpeg_progressive,
+        });
+        candidates.push(Candidate {
+            label: "mozjpeg-progressive-420",
+            quality,
+            encode: encode_with_mozjpeg_progressive_subsampled,
+        });
+    }
+
+    candidates
+}
+
+/// 複数の候補エンコードを並行して試し、正常にデコードできる中で最小の結果を返す
+///
+/// 候補ごとの結果サイズを `best_len`（アトミック）で共有し、既に見つかっている
+/// 最小サイズ以上の候補は、デコード検証という重い処理を省いて早期に切り捨てる。
+fn optimize_jpeg_file(img: &DynamicImage, output_path: &Path, base_quality: u8) -> Result<Vec<u8>> {
+    let candidates = build_candidates(base_quality);
+    let best_len = AtomicUsize::new(usize::MAX);
+
+    let winner = candidates
+        .par_iter()
+        .filter_map(|candidate| {
+            let encoded = (candidate.encode)(img, candidate.quality).ok()?;
+
+            if encoded.len() >= best_len.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            // 出力が正常にデコードできることを確認してから採用候補とする
+            if image::load_from_memory(&encoded).is_err() {
+                warn!(
+                    "候補エンコード({}, 品質{})のデコード検証に失敗したため破棄します: {}",
+                    candidate.label,
+                    candidate.quality,
+                    output_path.display()
+                );
+                return None;
+            }
+
+            best_len.fetch_min(encoded.len(), Ordering::Relaxed);
+            Some((candidate.label, candidate.quality, encoded))
+        })
+        .reduce_with(|a, b| if a.2.len() <= b.2.len() { a } else { b })
+        .with_context(|| {
+            format!(
+                "有効な候補エンコードが得られませんでした: {}",
+                output_path.display()
+            )
+        })?;
+
+    info!(
+        "最適化候補を採用: {} (バックエンド={}, 品質={}, サイズ={}バイト)",
+        output_path.display(),
+        winner.0,
+        winner.1,
+        winner.2.len()
+    );
+
+    Ok(winner.2)
+}
+
+/// imageクレートのJpegEncoderを使用したエンコード実装
+fn encode_with_image_crate(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut buffer, quality);
     encoder
-        .encode_image(&img)
-        .with_context(|| "JPEG エンコードに失敗しました")?;
+        .encode_image(img)
+        .with_context(|| "画像のエンコードに失敗しました")?;
+    Ok(buffer)
+}
 
-    info!("JPEG として保存しました: {}", output_path.display());
-    Ok(())
+/// mozjpegを使用したベースライン（非プログレッシブ・クロマサブサンプリングなし）エンコード実装
+#[cfg(feature = "mozjpeg-encoder")]
+fn encode_with_mozjpeg_baseline(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    encode_with_mozjpeg(img, quality, false, false)
+}
+
+/// mozjpegを使用したベースライン（非プログレッシブ・4:2:0クロマサブサンプリング）エンコード実装
+#[cfg(feature = "mozjpeg-encoder")]
+fn encode_with_mozjpeg_baseline_subsampled(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    encode_with_mozjpeg(img, quality, false, true)
+}
+
+/// mozjpegを使用したプログレッシブ（クロマサブサンプリングなし）エンコード実装
+#[cfg(feature = "mozjpeg-encoder")]
+fn encode_with_mozjpeg_progressive(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    encode_with_mozjpeg(img, quality, true, false)
+}
+
+/// mozjpegを使用したプログレッシブ（4:2:0クロマサブサンプリング）エンコード実装
+#[cfg(feature = "mozjpeg-encoder")]
+fn encode_with_mozjpeg_progressive_subsampled(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    encode_with_mozjpeg(img, quality, true, true)
+}
+
+#[cfg(feature = "mozjpeg-encoder")]
+fn encode_with_mozjpeg(
+    img: &DynamicImage,
+    quality: u8,
+    progressive: bool,
+    subsample: bool,
+) -> Result<Vec<u8>> {
+    use mozjpeg::{ColorSpace, Compress};
+
+    let rgb_img = img.to_rgb8();
+    let width = rgb_img.width() as usize;
+    let height = rgb_img.height() as usize;
+    let pixels = rgb_img.into_raw();
+
+    let mut buffer = Vec::new();
+
+    let mut comp = Compress::new(ColorSpace::JCS_RGB);
+    comp.set_size(width, height);
+    comp.set_quality(quality as f32);
+    comp.set_optimize_coding(true);
+    if progressive {
+        comp.set_progressive_mode();
+    }
+    // 4:2:0（サブサンプリングあり）はクロマ1サンプルが輝度2x2ピクセル分、4:4:4（なし）は1x1
+    let sampling_factor = if subsample { 2 } else { 1 };
+    comp.set_chroma_sampling_pixel_size(sampling_factor, sampling_factor);
+
+    let mut comp_started = comp
+        .start_compress(&mut buffer)
+        .with_context(|| "mozjpegの圧縮開始に失敗しました")?;
+    comp_started
+        .write_scanlines(&pixels)
+        .with_context(|| "画像データの書き込みに失敗しました")?;
+    comp_started
+        .finish()
+        .with_context(|| "mozjpegの圧縮完了に失敗しました")?;
+
+    Ok(buffer)
 }